@@ -0,0 +1,77 @@
+//! Companion proc-macro crate to `regex-matcher`: the [`regex!`] macro
+//! validates and compiles a pattern literal at compile time, so a malformed
+//! pattern is a compile error rather than a runtime `false`, and callers
+//! never pay to tokenize the same literal twice.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, LitStr};
+
+use regex_matcher::rs::parse;
+use regex_matcher::rs::program::{self, ClassSpec, Inst};
+
+/// `regex!("^a.*b$")` expands to a `move |text: &str| -> bool` closure that
+/// runs the pattern's pre-compiled program against `text`, with the same
+/// semantics as [`regex_matcher::rs::match_regexp`]. The pattern is parsed
+/// and compiled once, here, at macro-expansion time.
+#[proc_macro]
+pub fn regex(input: TokenStream) -> TokenStream {
+    let pattern = parse_macro_input!(input as LitStr);
+    let regexp = pattern.value();
+
+    let tokens = match parse::parse(&regexp) {
+        Ok(tokens) => tokens,
+        Err(err) => {
+            return syn::Error::new(pattern.span(), format!("invalid pattern: {err}"))
+                .to_compile_error()
+                .into();
+        }
+    };
+
+    let slot_count = 2 * (parse::group_count(&tokens) + 1);
+    let compiled = program::compile(&tokens);
+    let insts = compiled.insts.iter().map(inst_tokens);
+    let classes = compiled.classes.iter().map(class_tokens);
+
+    // `__INSTS`/`__CLASSES` are `static` array literals, not `Vec`s, so the
+    // program is never built on the heap; each call of the generated closure
+    // still allocates `program::run`'s working thread lists.
+    quote! {
+        {
+            static __INSTS: &[::regex_matcher::rs::program::Inst] = &[#(#insts),*];
+            static __CLASSES: &[::regex_matcher::rs::program::ClassSpec] = &[#(#classes),*];
+            let __prog = ::regex_matcher::rs::program::CompiledProgram {
+                insts: ::std::borrow::Cow::Borrowed(__INSTS),
+                classes: ::std::borrow::Cow::Borrowed(__CLASSES),
+            };
+            move |text: &str| ::regex_matcher::rs::program::run(&__prog, #slot_count, text).is_some()
+        }
+    }
+    .into()
+}
+
+fn inst_tokens(inst: &Inst) -> TokenStream2 {
+    match *inst {
+        Inst::Char(c) => quote! { ::regex_matcher::rs::program::Inst::Char(#c) },
+        Inst::AnyChar => quote! { ::regex_matcher::rs::program::Inst::AnyChar },
+        Inst::Class(idx) => quote! { ::regex_matcher::rs::program::Inst::Class(#idx) },
+        Inst::AssertStart => quote! { ::regex_matcher::rs::program::Inst::AssertStart },
+        Inst::AssertEnd => quote! { ::regex_matcher::rs::program::Inst::AssertEnd },
+        Inst::Save(slot) => quote! { ::regex_matcher::rs::program::Inst::Save(#slot) },
+        Inst::Jump(to) => quote! { ::regex_matcher::rs::program::Inst::Jump(#to) },
+        Inst::Split(x, y) => quote! { ::regex_matcher::rs::program::Inst::Split(#x, #y) },
+        Inst::Match => quote! { ::regex_matcher::rs::program::Inst::Match },
+    }
+}
+
+fn class_tokens(class: &ClassSpec) -> TokenStream2 {
+    let negated = class.negated;
+    let ranges = class.ranges.iter().map(|&(lo, hi)| quote! { (#lo, #hi) });
+    quote! {
+        ::regex_matcher::rs::program::ClassSpec {
+            negated: #negated,
+            ranges: ::std::borrow::Cow::Borrowed(&[#(#ranges),*]),
+        }
+    }
+}
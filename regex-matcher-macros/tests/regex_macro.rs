@@ -0,0 +1,16 @@
+use regex_matcher_macros::regex;
+
+#[test]
+fn matches_and_rejects_as_expected() {
+    let is_match = regex!("^a.*b$");
+    assert!(is_match("ab"));
+    assert!(is_match("aXXXb"));
+    assert!(!is_match("xab"));
+    assert!(!is_match("a"));
+}
+
+#[test]
+fn an_invalid_pattern_is_a_compile_error() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/invalid_pattern.rs");
+}
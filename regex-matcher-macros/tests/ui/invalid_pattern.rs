@@ -0,0 +1,3 @@
+fn main() {
+    let _ = regex_matcher_macros::regex!("*a");
+}
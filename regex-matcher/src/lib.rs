@@ -76,84 +76,723 @@ pub mod book {
     }
 }
 
-/// rs contains a reimplementation of the source code from the book which has
-/// been modernised, but is more-or-less an equivalent algorithm.
+/// rs is a from-scratch reimplementation that replaces the book's recursive
+/// backtracking matcher with a validating parser, a `program` module
+/// compiling patterns down to a small instruction set, and a Pike's-VM
+/// executor that runs every live instruction in lockstep — trading the
+/// book's exponential worst case for `O(len(prog) * len(text))`. It also
+/// goes beyond what the book's matcher does: capture groups, character
+/// classes, `replace`, and matching a whole `RegexSet` in a single pass.
 pub mod rs {
+    /// checks if `regexp` matches `text`. Returns `false`, rather than
+    /// panicking, if `regexp` isn't a valid pattern — use [`parse::parse`]
+    /// directly if you need to know why it was rejected.
     pub fn match_regexp(regexp: &str, text: &str) -> bool {
-        use parse::Tokenize;
+        match parse::parse(regexp) {
+            Ok(tokens) => Matcher::compile(&tokens).is_match(text),
+            Err(_) => false,
+        }
+    }
+
+    /// Finds the leftmost match of `regexp` in `text`, if any, along with the
+    /// spans of any capture groups it contains. Returns `None`, rather than
+    /// panicking, if `regexp` isn't a valid pattern.
+    pub fn find(regexp: &str, text: &str) -> Option<Match> {
+        let tokens = parse::parse(regexp).ok()?;
+        Matcher::compile(&tokens).find(text)
+    }
+
+    /// Replaces the leftmost match of `regexp` in `text` with `template`
+    /// expanded against that match's captures, or returns `text` unchanged
+    /// if `regexp` doesn't match (or isn't a valid pattern). In `template`,
+    /// `$0` expands to the whole match, `$1`, `$2`, ... expand to the
+    /// corresponding capture group (or nothing, if that group took no part
+    /// in the match), and `$$` is a literal `$`.
+    pub fn replace(regexp: &str, text: &str, template: &str) -> String {
+        match find(regexp, text) {
+            Some(m) => {
+                let mut out = String::with_capacity(text.len());
+                out.push_str(&text[..m.start]);
+                out.push_str(&expand(&m, text, template));
+                out.push_str(&text[m.end..]);
+                out
+            }
+            None => text.to_string(),
+        }
+    }
+
+    /// Like [`replace`], but replaces every non-overlapping match of
+    /// `regexp` in `text`. A zero-width match is followed by copying the
+    /// next char verbatim, so replacement always makes forward progress
+    /// instead of looping on the same position forever.
+    pub fn replace_all(regexp: &str, text: &str, template: &str) -> String {
+        let tokens = match parse::parse(regexp) {
+            Ok(tokens) => tokens,
+            Err(_) => return text.to_string(),
+        };
+        let matcher = Matcher::compile(&tokens);
+        // each remainder is searched as if it were the whole text, so a `^`
+        // pattern must stop after the attempt at position 0.
+        let anchored = matches!(tokens.first(), Some(parse::Token::Start));
+
+        let mut out = String::new();
+        let mut pos = 0;
+        while pos <= text.len() {
+            if anchored && pos > 0 {
+                break;
+            }
+            let rest = &text[pos..];
+            let Some(m) = matcher.find(rest) else {
+                break;
+            };
+
+            out.push_str(&rest[..m.start]);
+            out.push_str(&expand(&m, rest, template));
+
+            if m.end > m.start {
+                pos += m.end;
+                continue;
+            }
+
+            match rest[m.end..].chars().next() {
+                Some(c) => {
+                    out.push(c);
+                    pos += m.end + c.len_utf8();
+                }
+                None => {
+                    pos += m.end;
+                    break;
+                }
+            }
+        }
+        out.push_str(&text[pos..]);
+        out
+    }
+
+    /// Expands `template` against `m`, a match found in `haystack`.
+    fn expand(m: &Match, haystack: &str, template: &str) -> String {
+        let mut out = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '$' {
+                out.push(c);
+                continue;
+            }
+
+            match chars.peek() {
+                Some('$') => {
+                    chars.next();
+                    out.push('$');
+                }
+                Some(d) if d.is_ascii_digit() => {
+                    let mut digits = String::new();
+                    while chars.peek().is_some_and(char::is_ascii_digit) {
+                        digits.push(chars.next().unwrap());
+                    }
+                    // a digit run longer than any real group count just
+                    // means "no such group" - clamp instead of panicking on
+                    // overflow.
+                    let group: usize = digits.parse().unwrap_or(usize::MAX);
+                    let span = if group == 0 {
+                        Some((m.start, m.end))
+                    } else {
+                        m.groups.get(group - 1).copied().flatten()
+                    };
+                    if let Some((start, end)) = span {
+                        out.push_str(&haystack[start..end]);
+                    }
+                }
+                _ => out.push('$'),
+            }
+        }
+        out
+    }
 
-        Matcher::new(regexp.tokenize()).is_match(text)
+    /// The span of a match, plus the span of each `(...)` capture group in
+    /// the pattern, in the order the groups were opened. A group that took
+    /// no part in the match is `None`.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct Match {
+        pub start: usize,
+        pub end: usize,
+        pub groups: Vec<Option<(usize, usize)>>,
     }
 
-    #[derive(Clone)]
-    struct Matcher<Ts: Iterator<Item = parse::Token>> {
-        tokens: std::iter::Peekable<Ts>,
+    /// A pattern compiled down to a [`program::CompiledProgram`], ready to be
+    /// run against any number of inputs without re-tokenizing.
+    struct Matcher {
+        prog: program::CompiledProgram,
+        /// how many `(...)` groups `prog` saves captures for.
+        group_count: usize,
     }
 
-    impl<Ts: Iterator<Item = parse::Token> + Clone> Matcher<Ts> {
-        fn new(tokens: Ts) -> Matcher<Ts> {
+    impl Matcher {
+        fn compile(tokens: &[parse::Token]) -> Self {
             Self {
-                tokens: tokens.peekable(),
+                prog: program::compile(tokens),
+                group_count: parse::group_count(tokens),
             }
         }
 
-        fn is_match(mut self, text: &str) -> bool {
-            match self.tokens.peek() {
-                Some(parse::Token::Start) => Matcher::new(self.tokens.skip(1)).match_here(text),
-                _ => self.match_rest(text),
+        fn is_match(&self, text: &str) -> bool {
+            self.run(text).is_some()
+        }
+
+        fn find(&self, text: &str) -> Option<Match> {
+            let saved = self.run(text)?;
+            Some(Match {
+                start: saved[0]?,
+                end: saved[1]?,
+                groups: (0..self.group_count)
+                    .map(|group| Some((saved[2 * (group + 1)]?, saved[2 * (group + 1) + 1]?)))
+                    .collect(),
+            })
+        }
+
+        /// slot `0`/`1` are reserved for the overall match's start/end; a
+        /// user-visible group `k` is saved in slots `2*(k+1)`/`2*(k+1)+1`.
+        fn run(&self, text: &str) -> Option<Vec<Option<usize>>> {
+            program::run(&self.prog, 2 * (self.group_count + 1), text)
+        }
+    }
+
+    /// Tests many patterns against one input in a single pass, rather than
+    /// looping over [`match_regexp`] once per pattern.
+    pub struct RegexSet {
+        prog: set::SetProgram,
+    }
+
+    impl RegexSet {
+        /// Compiles `patterns` into one concatenated program. Fails on the
+        /// first pattern that doesn't parse.
+        pub fn new(patterns: &[&str]) -> Result<Self, parse::ParseError> {
+            Ok(Self {
+                prog: set::compile(patterns)?,
+            })
+        }
+
+        /// Returns the index of every pattern `RegexSet::new` was given that
+        /// matches somewhere in `text`, in pattern order.
+        pub fn matches(&self, text: &str) -> Vec<usize> {
+            set::run(&self.prog, text)
+        }
+    }
+
+    /// program compiles a token stream into a small instruction set and
+    /// executes it with Pike's VM: every live program counter is advanced one
+    /// input character at a time, in lockstep, rather than recursively trying
+    /// (and backtracking through) one possibility at a time. This keeps
+    /// matching `O(len(prog) * len(text))` even for patterns like
+    /// `a*a*a*a*b` that make the old recursive matcher blow up.
+    ///
+    /// `Inst`, `ClassSpec`, `CompiledProgram`, [`compile`] and [`run`] are
+    /// `pub` (rather than `pub(super)`) so that companion crates — e.g. the
+    /// `regex!` proc macro — can compile a pattern once, at macro-expansion
+    /// time, and embed the resulting program as a literal instead of paying
+    /// for tokenization on every call.
+    pub mod program {
+        use std::borrow::Cow;
+
+        use super::parse::{Single, Token};
+
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub enum Inst {
+            Char(char),
+            AnyChar,
+            /// an index into the program's `classes` table.
+            Class(usize),
+            AssertStart,
+            AssertEnd,
+            Save(usize),
+            Jump(usize),
+            Split(usize, usize),
+            Match,
+        }
+
+        /// A `[...]` character class: matches any char covered by one of
+        /// `ranges` (a single literal char is just a range of one), XORed
+        /// with `negated`. `ranges` is a `Cow` rather than a plain `Vec` so
+        /// that code compiling a pattern at compile time (the `regex!` macro)
+        /// can embed it as a `&'static` array literal with no heap
+        /// allocation, while [`compile`] still builds one at runtime same as
+        /// before.
+        #[derive(Clone, Debug, PartialEq, Eq)]
+        pub struct ClassSpec {
+            pub negated: bool,
+            pub ranges: Cow<'static, [(char, char)]>,
+        }
+
+        impl ClassSpec {
+            pub(crate) fn contains(&self, c: char) -> bool {
+                let in_ranges = self.ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi);
+                in_ranges != self.negated
             }
         }
 
-        fn match_rest(&mut self, text: &str) -> bool {
-            if self.clone().match_here(text) {
-                true
-            } else if text.is_empty() {
-                false
-            } else {
-                self.match_rest(&text[1..])
+        /// A compiled pattern: the instruction sequence, plus the class
+        /// table `Inst::Class` indexes into (kept out-of-line so `Inst`
+        /// itself stays a cheap, `Copy` word). Both fields are `Cow` for the
+        /// same reason as [`ClassSpec::ranges`]: the `regex!` macro embeds
+        /// them as `&'static` array literals, so the program itself is never
+        /// built on the heap, while [`compile`] still builds owned `Vec`s.
+        /// [`run`] allocates its own working lists on every call either way.
+        pub struct CompiledProgram {
+            pub insts: Cow<'static, [Inst]>,
+            pub classes: Cow<'static, [ClassSpec]>,
+        }
+
+        /// Compiles `tokens` into a program, terminated by a `Match`.
+        ///
+        /// A literal `c` or `.` becomes a single `Char`/`AnyChar`
+        /// instruction. `c*` becomes the standard star construction:
+        /// `L1: Split(L2, L3); L2: <c>; Jump(L1); L3:`, so the VM can choose
+        /// between "match once more" and "stop here" without recursing.
+        /// `(`/`)` bracket their group's body with `Save` instructions that
+        /// record the group's start/end byte offset; the whole match is
+        /// bracketed the same way, in slots `0`/`1`.
+        pub fn compile(tokens: &[Token]) -> CompiledProgram {
+            let mut insts = vec![Inst::Save(0)];
+            let mut classes = Vec::new();
+            for tok in tokens {
+                match tok {
+                    Token::Start => insts.push(Inst::AssertStart),
+                    Token::End => insts.push(Inst::AssertEnd),
+                    Token::Single(single) => push_single(&mut insts, &mut classes, single.clone()),
+                    Token::GroupStart(group) => insts.push(Inst::Save(2 * (*group + 1))),
+                    Token::GroupEnd(group) => insts.push(Inst::Save(2 * (*group + 1) + 1)),
+                    Token::ZeroOrMore(single) => {
+                        let split = insts.len();
+                        insts.push(Inst::Split(0, 0)); // backpatched once we know L2/L3
+                        let l2 = insts.len();
+                        push_single(&mut insts, &mut classes, single.clone());
+                        insts.push(Inst::Jump(split));
+                        let l3 = insts.len();
+                        insts[split] = Inst::Split(l2, l3);
+                    }
+                }
+            }
+            insts.push(Inst::Save(1));
+            insts.push(Inst::Match);
+            CompiledProgram {
+                insts: Cow::Owned(insts),
+                classes: Cow::Owned(classes),
             }
         }
 
-        fn match_here(&mut self, text: &str) -> bool {
-            match self.tokens.next() {
-                None => true,
-                Some(parse::Token::ZeroOrMore(c)) => self.match_star(c, text),
-                Some(parse::Token::End) => text.is_empty(),
-                Some(parse::Token::Single(parse::Single::Any)) if !text.is_empty() => {
-                    self.match_here(&text[1..])
+        fn push_single(insts: &mut Vec<Inst>, classes: &mut Vec<ClassSpec>, single: Single) {
+            insts.push(match single {
+                Single::Any => Inst::AnyChar,
+                Single::Literal(c) => Inst::Char(c),
+                Single::Class { negated, ranges } => {
+                    let idx = classes.len();
+                    classes.push(ClassSpec {
+                        negated,
+                        ranges: Cow::Owned(ranges),
+                    });
+                    Inst::Class(idx)
                 }
-                Some(parse::Token::Single(parse::Single::Literal(c))) if text.starts_with(c) => {
-                    self.match_here(&text[1..])
+            });
+        }
+
+        /// A saved slot is the byte offset a `Save` instruction was crossed
+        /// at, or `None` if that slot's group hasn't been entered/exited yet.
+        pub type Saved = Vec<Option<usize>>;
+
+        /// A live program counter, plus the capture slots it carried here.
+        struct Thread {
+            pc: usize,
+            saved: Saved,
+        }
+
+        /// The set of threads reachable at a given input position, deduped
+        /// via `seen` so a pattern like `a*a*a*` can't add the same pc twice
+        /// and blow up the thread count.
+        struct ThreadList {
+            threads: Vec<Thread>,
+            seen: Vec<bool>,
+        }
+
+        impl ThreadList {
+            fn new(prog_len: usize) -> Self {
+                Self {
+                    threads: Vec::new(),
+                    seen: vec![false; prog_len],
+                }
+            }
+
+            fn clear(&mut self) {
+                self.threads.clear();
+                self.seen.iter_mut().for_each(|s| *s = false);
+            }
+        }
+
+        /// Adds `pc` to `list`, following `Jump`/`Split`/assertions/`Save`
+        /// recursively until landing on a `Char`, `AnyChar`, `Class` or
+        /// `Match`.
+        /// `Save` clones `saved` with its slot updated, so divergent threads
+        /// never see each other's captures.
+        fn add_thread(
+            prog: &CompiledProgram,
+            list: &mut ThreadList,
+            pc: usize,
+            pos: usize,
+            text: &str,
+            saved: &Saved,
+        ) {
+            if list.seen[pc] {
+                return;
+            }
+            list.seen[pc] = true;
+
+            match prog.insts[pc] {
+                Inst::Jump(to) => add_thread(prog, list, to, pos, text, saved),
+                Inst::Split(x, y) => {
+                    add_thread(prog, list, x, pos, text, saved);
+                    add_thread(prog, list, y, pos, text, saved);
+                }
+                Inst::AssertStart if pos == 0 => add_thread(prog, list, pc + 1, pos, text, saved),
+                Inst::AssertEnd if pos == text.len() => {
+                    add_thread(prog, list, pc + 1, pos, text, saved)
+                }
+                Inst::AssertStart | Inst::AssertEnd => {} // assertion failed: thread dies here
+                Inst::Save(slot) => {
+                    let mut saved = saved.clone();
+                    saved[slot] = Some(pos);
+                    add_thread(prog, list, pc + 1, pos, text, &saved);
+                }
+                Inst::Char(_) | Inst::AnyChar | Inst::Class(_) | Inst::Match => {
+                    list.threads.push(Thread {
+                        pc,
+                        saved: saved.clone(),
+                    })
+                }
+            }
+        }
+
+        /// Runs `prog` against `text`, looking for a match starting from
+        /// some position in `text` (or only position `0`, if `prog` is
+        /// anchored with a leading `AssertStart`). On success, returns the
+        /// `slot_count` capture slots saved along the winning thread.
+        ///
+        /// `clist` is kept in priority order (earlier entries are preferred
+        /// alternatives), so reaching `Match` doesn't return immediately:
+        /// it's recorded as the best match so far, and any *lower*-priority
+        /// threads this step are dropped, but threads already ahead of it
+        /// keep running in case they produce a longer, still-preferred,
+        /// match on a later step.
+        pub fn run(prog: &CompiledProgram, slot_count: usize, text: &str) -> Option<Saved> {
+            // instruction 0 is always the whole-match `Save(0)`; the pattern
+            // is anchored if `^` immediately follows it.
+            let anchored = matches!(prog.insts.get(1), Some(Inst::AssertStart));
+            let chars: Vec<(usize, char)> = text.char_indices().collect();
+            let unsaved: Saved = vec![None; slot_count];
+
+            let mut clist = ThreadList::new(prog.insts.len());
+            let mut nlist = ThreadList::new(prog.insts.len());
+            add_thread(prog, &mut clist, 0, 0, text, &unsaved);
+
+            let mut matched: Option<Saved> = None;
+
+            for step in 0..=chars.len() {
+                let ch = chars.get(step).map(|&(_, c)| c);
+                let next_pos = chars.get(step + 1).map_or(text.len(), |&(i, _)| i);
+
+                nlist.clear();
+                for thread in &clist.threads {
+                    match prog.insts[thread.pc] {
+                        Inst::Char(c) if ch == Some(c) => add_thread(
+                            prog,
+                            &mut nlist,
+                            thread.pc + 1,
+                            next_pos,
+                            text,
+                            &thread.saved,
+                        ),
+                        Inst::AnyChar if ch.is_some() => add_thread(
+                            prog,
+                            &mut nlist,
+                            thread.pc + 1,
+                            next_pos,
+                            text,
+                            &thread.saved,
+                        ),
+                        Inst::Class(idx) if ch.is_some_and(|c| prog.classes[idx].contains(c)) => {
+                            add_thread(
+                                prog,
+                                &mut nlist,
+                                thread.pc + 1,
+                                next_pos,
+                                text,
+                                &thread.saved,
+                            )
+                        }
+                        Inst::Match => {
+                            matched = Some(thread.saved.clone());
+                            break;
+                        }
+                        _ => {}
+                    }
                 }
-                Some(parse::Token::Start) => panic!("$ token in illegal position"),
-                _ => false,
+                // unless anchored, a fresh (lowest-priority) attempt can
+                // also start here - unless something has matched already,
+                // in which case no later start could ever outrank it.
+                if !anchored && matched.is_none() {
+                    add_thread(prog, &mut nlist, 0, next_pos, text, &unsaved);
+                }
+
+                std::mem::swap(&mut clist, &mut nlist);
             }
+
+            matched
+        }
+    }
+
+    /// set concatenates several patterns' compiled programs into one, so
+    /// `RegexSet::matches` can try every pattern's threads at once instead
+    /// of scanning `text` once per pattern. Unlike [`program`], it doesn't
+    /// track captures: `RegexSet` only reports *which* patterns matched.
+    mod set {
+        use super::parse;
+        use super::program::{self, ClassSpec, Inst};
+
+        /// The concatenation of every pattern's instructions and classes,
+        /// plus the pc of each pattern's `Match` instruction, in pattern
+        /// order, so a thread reaching `Match` can be traced back to which
+        /// pattern it belongs to.
+        pub(super) struct SetProgram {
+            insts: Vec<Inst>,
+            classes: Vec<ClassSpec>,
+            match_pcs: Vec<usize>,
         }
 
-        fn match_star(&self, c: parse::Single, text: &str) -> bool {
-            if self.clone().match_here(text) {
-                true
-            } else if text.is_empty() {
-                false
-            } else {
-                self.match_star(c, &text[1..])
+        pub(super) fn compile(patterns: &[&str]) -> Result<SetProgram, parse::ParseError> {
+            let mut insts = Vec::new();
+            let mut classes = Vec::new();
+            let mut match_pcs = Vec::new();
+
+            for pattern in patterns {
+                let tokens = parse::parse(pattern)?;
+                let sub = program::compile(&tokens);
+
+                let inst_offset = insts.len();
+                let class_offset = classes.len();
+                insts.extend(
+                    sub.insts
+                        .iter()
+                        .map(|inst| shift(*inst, inst_offset, class_offset)),
+                );
+                classes.extend(sub.classes.iter().cloned());
+
+                match_pcs.push(insts.len() - 1); // the `Match` just appended
+            }
+
+            Ok(SetProgram {
+                insts,
+                classes,
+                match_pcs,
+            })
+        }
+
+        /// rewrites a sub-program's internal pcs/class indices to where it
+        /// now lives in the concatenated program.
+        fn shift(inst: Inst, inst_offset: usize, class_offset: usize) -> Inst {
+            match inst {
+                Inst::Jump(to) => Inst::Jump(to + inst_offset),
+                Inst::Split(x, y) => Inst::Split(x + inst_offset, y + inst_offset),
+                Inst::Class(idx) => Inst::Class(idx + class_offset),
+                other => other,
+            }
+        }
+
+        /// the pc each pattern starts running from: pattern `0` starts at
+        /// `0`, and every later pattern starts right after the previous
+        /// pattern's `Match`.
+        fn start_pcs(prog: &SetProgram) -> Vec<usize> {
+            std::iter::once(0)
+                .chain(prog.match_pcs.iter().map(|&pc| pc + 1))
+                .take(prog.match_pcs.len())
+                .collect()
+        }
+
+        /// a pattern is anchored if `^` immediately follows the `Save(0)`
+        /// its sub-program starts with.
+        fn is_anchored(prog: &SetProgram, start: usize) -> bool {
+            matches!(prog.insts.get(start + 1), Some(Inst::AssertStart))
+        }
+
+        /// Like [`program`]'s `ThreadList`, but threads here are bare pcs:
+        /// `RegexSet` never needs to thread captures through, since it only
+        /// reports which patterns matched, not where.
+        struct ThreadList {
+            pcs: Vec<usize>,
+            seen: Vec<bool>,
+        }
+
+        impl ThreadList {
+            fn new(prog_len: usize) -> Self {
+                Self {
+                    pcs: Vec::new(),
+                    seen: vec![false; prog_len],
+                }
+            }
+
+            fn clear(&mut self) {
+                self.pcs.clear();
+                self.seen.iter_mut().for_each(|s| *s = false);
+            }
+        }
+
+        fn add_thread(prog: &SetProgram, list: &mut ThreadList, pc: usize, pos: usize, text: &str) {
+            if list.seen[pc] {
+                return;
+            }
+            list.seen[pc] = true;
+
+            match prog.insts[pc] {
+                Inst::Jump(to) => add_thread(prog, list, to, pos, text),
+                Inst::Split(x, y) => {
+                    add_thread(prog, list, x, pos, text);
+                    add_thread(prog, list, y, pos, text);
+                }
+                Inst::AssertStart if pos == 0 => add_thread(prog, list, pc + 1, pos, text),
+                Inst::AssertEnd if pos == text.len() => add_thread(prog, list, pc + 1, pos, text),
+                Inst::AssertStart | Inst::AssertEnd => {} // assertion failed: thread dies here
+                Inst::Save(_) => add_thread(prog, list, pc + 1, pos, text), // captures unused
+                Inst::Char(_) | Inst::AnyChar | Inst::Class(_) | Inst::Match => list.pcs.push(pc),
+            }
+        }
+
+        /// Runs every pattern's threads over `text` in one pass, returning
+        /// the index of every pattern whose `Match` was reached.
+        pub(super) fn run(prog: &SetProgram, text: &str) -> Vec<usize> {
+            let chars: Vec<(usize, char)> = text.char_indices().collect();
+            let starts = start_pcs(prog);
+            let anchored: Vec<bool> = starts.iter().map(|&start| is_anchored(prog, start)).collect();
+
+            let mut clist = ThreadList::new(prog.insts.len());
+            let mut nlist = ThreadList::new(prog.insts.len());
+            for &start in &starts {
+                add_thread(prog, &mut clist, start, 0, text);
             }
+
+            let mut matched = vec![false; prog.match_pcs.len()];
+
+            for step in 0..=chars.len() {
+                let ch = chars.get(step).map(|&(_, c)| c);
+                let next_pos = chars.get(step + 1).map_or(text.len(), |&(i, _)| i);
+
+                nlist.clear();
+                for &pc in &clist.pcs {
+                    match prog.insts[pc] {
+                        Inst::Char(c) if ch == Some(c) => {
+                            add_thread(prog, &mut nlist, pc + 1, next_pos, text)
+                        }
+                        Inst::AnyChar if ch.is_some() => {
+                            add_thread(prog, &mut nlist, pc + 1, next_pos, text)
+                        }
+                        Inst::Class(idx) if ch.is_some_and(|c| prog.classes[idx].contains(c)) => {
+                            add_thread(prog, &mut nlist, pc + 1, next_pos, text)
+                        }
+                        Inst::Match => {
+                            if let Some(pattern) = prog.match_pcs.iter().position(|&m| m == pc) {
+                                matched[pattern] = true;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                // a pattern that hasn't matched yet can still start a fresh
+                // attempt here; one that has matched gains nothing from it,
+                // and nor does one that's anchored, since an anchored
+                // pattern can only ever match starting at position 0.
+                for (pattern, &start) in starts.iter().enumerate() {
+                    if !matched[pattern] && !anchored[pattern] {
+                        add_thread(prog, &mut nlist, start, next_pos, text);
+                    }
+                }
+
+                std::mem::swap(&mut clist, &mut nlist);
+            }
+
+            matched
+                .into_iter()
+                .enumerate()
+                .filter_map(|(pattern, hit)| hit.then_some(pattern))
+                .collect()
         }
     }
 
-    mod parse {
-        #[derive(Copy, Clone, Debug)]
+    pub mod parse {
+        /// Parses `regexp` into a token stream, or the first grammar error
+        /// found, tagged with the byte offset it occurred at.
+        pub fn parse(regexp: &str) -> Result<Vec<Token>, ParseError> {
+            regexp.tokenize().collect()
+        }
+
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub struct ParseError {
+            pub position: usize,
+            pub kind: ParseErrorKind,
+        }
+
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub enum ParseErrorKind {
+            /// a `*` with no preceding single char/class to repeat; a `*`
+            /// right after a group's `)` is also this error.
+            UnexpectedStar,
+            /// a `^` anywhere but the first character of the pattern.
+            MisplacedStart,
+            /// a `$` anywhere but the last character of the pattern.
+            MisplacedEnd,
+            /// a `[` with no matching `]`.
+            UnterminatedClass,
+            /// a `(` with no matching `)`.
+            UnterminatedGroup,
+        }
+
+        impl ParseError {
+            fn at(position: usize, kind: ParseErrorKind) -> Self {
+                Self { position, kind }
+            }
+        }
+
+        impl std::fmt::Display for ParseError {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let what = match self.kind {
+                    ParseErrorKind::UnexpectedStar => "unexpected `*`",
+                    ParseErrorKind::MisplacedStart => "`^` is only valid at the start of a pattern",
+                    ParseErrorKind::MisplacedEnd => "`$` is only valid at the end of a pattern",
+                    ParseErrorKind::UnterminatedClass => "unterminated `[`",
+                    ParseErrorKind::UnterminatedGroup => "unterminated `(`",
+                };
+                write!(f, "{what} at position {}", self.position)
+            }
+        }
+
+        impl std::error::Error for ParseError {}
+
+        #[derive(Clone, Debug)]
         pub enum Token {
             Single(Single),
             Start,
             End,
             ZeroOrMore(Single),
+            /// the `usize` is the group's number, assigned in the order its
+            /// `(` was opened.
+            GroupStart(usize),
+            GroupEnd(usize),
         }
 
-        #[derive(Copy, Clone, Debug)]
+        #[derive(Clone, Debug)]
         pub enum Single {
             Any,
             Literal(char),
+            /// a `[...]` class: matches a char in (or, if `negated`, not in)
+            /// any of `ranges`.
+            Class {
+                negated: bool,
+                ranges: Vec<(char, char)>,
+            },
         }
 
         impl From<char> for Single {
@@ -165,34 +804,152 @@ pub mod rs {
             }
         }
 
+        /// the number of `(...)` groups present in `tokens`.
+        pub fn group_count(tokens: &[Token]) -> usize {
+            tokens
+                .iter()
+                .filter_map(|tok| match tok {
+                    Token::GroupStart(group) => Some(group + 1),
+                    _ => None,
+                })
+                .max()
+                .unwrap_or(0)
+        }
+
         pub trait Tokenize {
             fn tokenize(&self) -> Tokens<'_>;
         }
 
         impl Tokenize for str {
             fn tokenize<'src>(&'src self) -> Tokens<'src> {
-                Tokens(self.chars().peekable())
+                Tokens {
+                    chars: self.chars().peekable(),
+                    pos: 0,
+                    next_group: 0,
+                    open_groups: Vec::new(),
+                }
             }
         }
 
         #[derive(Clone)]
-        pub struct Tokens<'src>(std::iter::Peekable<std::str::Chars<'src>>);
+        pub struct Tokens<'src> {
+            chars: std::iter::Peekable<std::str::Chars<'src>>,
+            /// the byte offset of the next unconsumed char, for error
+            /// reporting.
+            pos: usize,
+            next_group: usize,
+            /// the number and opening byte offset of each `(` not yet
+            /// matched by a `)`.
+            open_groups: Vec<(usize, usize)>,
+        }
 
         impl<'src> Iterator for Tokens<'src> {
-            type Item = Token;
+            type Item = Result<Token, ParseError>;
 
             fn next(&mut self) -> Option<Self::Item> {
-                self.0.next().map(|nxt| match nxt {
-                    '^' => Token::Start,
-                    '$' => Token::End,
-                    c if self.0.peek().copied() == Some('*') => {
-                        self.0.next();
-                        Token::ZeroOrMore(c.into())
+                let start = self.pos;
+                let nxt = match self.bump() {
+                    Some(c) => c,
+                    // running out of input with a `(` still open is an
+                    // error, the same way an unterminated `[` is; clearing
+                    // `open_groups` ensures we report it only once.
+                    None => {
+                        return self.open_groups.first().copied().map(|(_, pos)| {
+                            self.open_groups.clear();
+                            Err(ParseError::at(pos, ParseErrorKind::UnterminatedGroup))
+                        });
+                    }
+                };
+                Some(match nxt {
+                    '^' if start != 0 => Err(ParseError::at(start, ParseErrorKind::MisplacedStart)),
+                    '^' => Ok(Token::Start),
+                    '$' if self.chars.peek().is_some() => {
+                        Err(ParseError::at(start, ParseErrorKind::MisplacedEnd))
+                    }
+                    '$' => Ok(Token::End),
+                    '*' => Err(ParseError::at(start, ParseErrorKind::UnexpectedStar)),
+                    '(' => {
+                        let group = self.next_group;
+                        self.next_group += 1;
+                        self.open_groups.push((group, start));
+                        Ok(Token::GroupStart(group))
                     }
-                    c => Token::Single(c.into()),
+                    ')' => match self.open_groups.pop() {
+                        Some((group, _)) => Ok(Token::GroupEnd(group)),
+                        None => Ok(self.wrap_single(')'.into())),
+                    },
+                    '[' => self
+                        .parse_class(start)
+                        .map(|single| self.wrap_single(single)),
+                    c => Ok(self.wrap_single(c.into())),
                 })
             }
         }
+
+        impl<'src> Tokens<'src> {
+            /// consumes and returns the next char, advancing `pos` by its
+            /// byte length.
+            fn bump(&mut self) -> Option<char> {
+                let c = self.chars.next()?;
+                self.pos += c.len_utf8();
+                Some(c)
+            }
+
+            /// wraps `single` as `ZeroOrMore` if it's followed by a `*`,
+            /// otherwise as a plain `Single`.
+            fn wrap_single(&mut self, single: Single) -> Token {
+                if self.chars.peek() == Some(&'*') {
+                    self.bump();
+                    Token::ZeroOrMore(single)
+                } else {
+                    Token::Single(single)
+                }
+            }
+
+            /// Parses the body of a `[...]` bracket expression, having
+            /// already consumed the opening `[` at byte offset `start`.
+            /// `]` is only a terminator once at least one char has been
+            /// consumed (so `[]a]` treats the first `]` as a literal), and a
+            /// `-` only introduces a range when a char follows it other
+            /// than the closing `]`. Running out of input before a
+            /// terminating `]` is a [`ParseErrorKind::UnterminatedClass`].
+            fn parse_class(&mut self, start: usize) -> Result<Single, ParseError> {
+                let negated = self.chars.peek() == Some(&'^');
+                if negated {
+                    self.bump();
+                }
+
+                let mut ranges = Vec::new();
+                let mut first = true;
+                loop {
+                    let c = self
+                        .bump()
+                        .ok_or(ParseError::at(start, ParseErrorKind::UnterminatedClass))?;
+                    if c == ']' && !first {
+                        break;
+                    }
+                    first = false;
+
+                    match self.chars.peek() {
+                        Some(&'-') => {
+                            let mut after_dash = self.chars.clone();
+                            after_dash.next();
+                            match after_dash.peek() {
+                                Some(&']') | None => ranges.push((c, c)),
+                                Some(&hi) => {
+                                    self.bump(); // the '-'
+                                    self.bump(); // `hi`
+                                    ranges.push((c, hi));
+                                }
+                            }
+                        }
+                        _ => ranges.push((c, c)),
+                    }
+                }
+
+                Ok(Single::Class { negated, ranges })
+            }
+        }
     }
 }
 
@@ -215,4 +972,164 @@ mod test {
             );
         }
     }
+
+    /// a pattern like `a*a*a*...b` against a string of just `a`s is the
+    /// textbook case that makes a recursive backtracking matcher (like
+    /// `book::match_regexp`) blow up exponentially, since it tries every
+    /// way of splitting the `a`s among the stars before giving up. the NFA
+    /// simulation in `rs` should stay fast regardless, since `ThreadList`
+    /// dedups pcs instead of branching per possibility. 30 stars is already
+    /// far more than a backtracker could finish in a human lifetime, so a
+    /// generous one-second budget is enough to catch a regression back to
+    /// exponential behaviour without the test being flaky under load.
+    #[test]
+    fn no_blowup_on_pathological_repetition() {
+        let pattern = "a*".repeat(30) + "b";
+        let text = "a".repeat(30);
+
+        let start = std::time::Instant::now();
+        let matched = rs::match_regexp(&pattern, &text);
+        let elapsed = start.elapsed();
+
+        assert!(!matched);
+        assert!(
+            elapsed < std::time::Duration::from_secs(1),
+            "expected linear-time matching, took {elapsed:?}"
+        );
+    }
+}
+
+#[cfg(test)]
+mod capture_test {
+    use crate::rs::find;
+
+    #[test]
+    fn captures_spans_of_every_group() {
+        let m = find("(a*)(b*)", "aaabbbc").unwrap();
+        assert_eq!((m.start, m.end), (0, 6));
+        assert_eq!(m.groups, vec![Some((0, 3)), Some((3, 6))]);
+    }
+
+    #[test]
+    fn nested_groups_are_captured_in_opening_order() {
+        let m = find("(a(b)c)", "xxabcyy").unwrap();
+        assert_eq!((m.start, m.end), (2, 5));
+        assert_eq!(m.groups, vec![Some((2, 5)), Some((3, 4))]);
+    }
+}
+
+#[cfg(test)]
+mod class_test {
+    use crate::rs::match_regexp;
+
+    #[test]
+    fn range() {
+        assert!(match_regexp("[a-z]*", "hello"));
+        assert!(!match_regexp("^[a-z]*$", "HELLO"));
+    }
+
+    #[test]
+    fn negated() {
+        assert!(match_regexp("^[^0-9]*$", "abc"));
+        assert!(!match_regexp("^[^0-9]*$", "a1c"));
+    }
+
+    #[test]
+    fn leading_bracket_is_literal() {
+        assert!(match_regexp("^[]a]*$", "]a]a"));
+    }
+
+    #[test]
+    fn trailing_dash_is_literal() {
+        assert!(match_regexp("^[a-]*$", "a-a-"));
+    }
+}
+
+#[cfg(test)]
+mod parse_error_test {
+    use crate::rs::parse::{parse, ParseErrorKind};
+
+    #[test]
+    fn unexpected_star() {
+        let err = parse("*a").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::UnexpectedStar);
+        assert_eq!(err.position, 0);
+    }
+
+    #[test]
+    fn misplaced_start() {
+        let err = parse("a^b").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::MisplacedStart);
+        assert_eq!(err.position, 1);
+    }
+
+    #[test]
+    fn misplaced_end() {
+        let err = parse("a$b").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::MisplacedEnd);
+        assert_eq!(err.position, 1);
+    }
+
+    #[test]
+    fn unterminated_class() {
+        let err = parse("[abc").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::UnterminatedClass);
+        assert_eq!(err.position, 0);
+    }
+
+    #[test]
+    fn unterminated_group() {
+        let err = parse("(abc").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::UnterminatedGroup);
+        assert_eq!(err.position, 0);
+    }
+
+    #[test]
+    fn well_formed_pattern_still_parses() {
+        assert!(parse("^(a)[0-9]*$").is_ok());
+    }
+}
+
+#[cfg(test)]
+mod regex_set_test {
+    use crate::rs::RegexSet;
+
+    #[test]
+    fn matches_reports_every_matching_pattern() {
+        let set = RegexSet::new(&["^a.*b$", "^[0-9]*z$", "cat"]).unwrap();
+        assert_eq!(set.matches("azzzb"), vec![0]);
+        assert_eq!(set.matches("123z"), vec![1]);
+        assert_eq!(set.matches("a cat sat on ab"), vec![0, 2]);
+        assert!(set.matches("nope").is_empty());
+    }
+
+    #[test]
+    fn invalid_pattern_is_an_error() {
+        assert!(RegexSet::new(&["good", "*bad"]).is_err());
+    }
+}
+
+#[cfg(test)]
+mod replace_test {
+    use crate::rs::{replace, replace_all};
+
+    #[test]
+    fn replace_expands_whole_match_and_groups() {
+        assert_eq!(replace("(a*)(b*)c", "xxaabbcyy", "$0:$2$1"), "xxaabbc:bbaayy");
+    }
+
+    #[test]
+    fn dollar_dollar_is_a_literal_dollar() {
+        assert_eq!(replace("a", "a", "$$"), "$");
+    }
+
+    #[test]
+    fn replace_all_advances_past_zero_width_matches() {
+        assert_eq!(replace_all("a*", "baaab", "-"), "-b--b-");
+    }
+
+    #[test]
+    fn overflowing_group_reference_expands_to_nothing_instead_of_panicking() {
+        assert_eq!(replace("a", "a", "$99999999999999999999"), "");
+    }
 }